@@ -0,0 +1,438 @@
+//! Core brainfuck interpreter: compiling source into a flat instruction
+//! stream and running it against injected `Read`/`Write` implementations.
+//! Kept free of any CLI/file concerns so it can be embedded and tested.
+//!
+//! Builds `no_std` (against `core` + `alloc` only) when the default `std`
+//! feature is disabled, for embedded/WASM-without-wasi targets. Without
+//! `std`, `Read`/`Write` are minimal local traits the caller implements
+//! over their own byte source/sink instead of `std::io`'s.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std::error;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use alloc::vec;
+
+#[cfg(feature = "std")]
+pub use std::io::{Read, Write};
+
+// Minimal byte source/sink for `no_std` builds: no `std::io::Error`, no
+// buffering, just enough for `Scan`/`Print` to drive.
+#[cfg(not(feature = "std"))]
+pub trait Read {
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, BfError>;
+}
+
+#[cfg(not(feature = "std"))]
+pub trait Write {
+	fn write_all(&mut self, buf: &[u8]) -> Result<(), BfError>;
+}
+
+// Standard brainf**k capactiy
+pub const CAPACITY: usize = 30000;
+
+// The main brainf**k memory
+pub struct Memory {
+	pub pointer: usize,
+	pub data: Vec<u16>,
+}
+
+impl Memory {
+	// Returns a new memory
+	pub fn new(capacity: usize) -> Memory {
+		Memory {
+			pointer: 0,
+			data: vec![0; capacity]
+		}
+	}
+
+	// Moves the pointer right by n cells according to the configured mode
+	pub fn inc_pointer(&mut self, n: usize, mode: &PointerMode, offset: usize) -> Result<(), BfError> {
+		let capacity = self.data.len();
+		self.pointer = match mode {
+			PointerMode::Standard => {
+				let next = self.pointer + n;
+				if next >= capacity {
+					return Err(BfError::PointerOutOfBounds { offset });
+				}
+				next
+			}
+			PointerMode::Saturating => (self.pointer + n).min(capacity - 1),
+			PointerMode::Wrapping => (self.pointer + n) % capacity,
+		};
+		Ok(())
+	}
+
+	// Moves the pointer left by n cells according to the configured mode
+	pub fn dec_pointer(&mut self, n: usize, mode: &PointerMode, offset: usize) -> Result<(), BfError> {
+		let capacity = self.data.len();
+		self.pointer = match mode {
+			PointerMode::Standard => {
+				self.pointer.checked_sub(n).ok_or(BfError::PointerOutOfBounds { offset })?
+			}
+			PointerMode::Saturating => self.pointer.saturating_sub(n),
+			PointerMode::Wrapping => {
+				let n = n % capacity;
+				if n <= self.pointer { self.pointer - n } else { capacity - (n - self.pointer) }
+			}
+		};
+		Ok(())
+	}
+
+	// Adds n to the current cell according to the configured mode
+	pub fn inc_value(&mut self, n: u16, mode: &CellMode) {
+		let cell = &mut self.data[self.pointer];
+		*cell = match mode {
+			CellMode::Standard => cell.checked_add(n).expect("Cell value overflow"),
+			CellMode::Saturating => cell.saturating_add(n),
+			CellMode::Wrapping => cell.wrapping_add(n),
+		};
+	}
+
+	// Subtracts n from the current cell according to the configured mode
+	pub fn dec_value(&mut self, n: u16, mode: &CellMode) {
+		let cell = &mut self.data[self.pointer];
+		*cell = match mode {
+			CellMode::Standard => cell.checked_sub(n).expect("Cell value underflow"),
+			CellMode::Saturating => cell.saturating_sub(n),
+			CellMode::Wrapping => cell.wrapping_sub(n),
+		};
+	}
+}
+
+// Cell overflow/underflow behavior for `+`/`-`
+pub enum CellMode {
+	// Panic on overflow/underflow, matching plain `u16` arithmetic
+	Standard,
+	// Clamp at the min/max cell value instead of over/underflowing
+	Saturating,
+	// Incrementing past the max value returns to 0 and vice versa
+	Wrapping,
+}
+
+// Pointer overflow/underflow behavior for `>`/`<`
+pub enum PointerMode {
+	// Error out on moving past the last cell or before the first one
+	Standard,
+	// Clamp at the first/last cell instead of over/underflowing
+	Saturating,
+	// Moving past the last cell wraps to the first and vice versa
+	Wrapping,
+}
+
+// Runtime options for the interpreter. Defaults to wrapping semantics for
+// both cells and the pointer, matching most canonical brainfuck
+// implementations.
+pub struct Config {
+	pub cell_mode: CellMode,
+	pub pointer_mode: PointerMode,
+}
+
+impl Default for Config {
+	fn default() -> Config {
+		Config {
+			cell_mode: CellMode::Wrapping,
+			pointer_mode: PointerMode::Wrapping,
+		}
+	}
+}
+
+// A single compiled instruction. Unlike the old Operation enum, loops are
+// not re-scanned on every pass: `[` and `]` are compiled once into a pair
+// of jumps with their targets already resolved. Runs of identical `+`/`-`/
+// `>`/`<` are fused into a single counted instruction by the peephole pass
+// below, and the `[-]` idiom is recognized as a dedicated ClearCell (`[+]`
+// is compiled as an ordinary loop, see compile()).
+pub enum Instr {
+	IncPtr(usize),
+	DecPtr(usize),
+	IncVal(u16),
+	DecVal(u16),
+	ClearCell,
+	Print,
+	Scan,
+	JumpIfZero(usize),
+	JumpIfNonZero(usize),
+}
+
+// A compiled program: the instruction stream plus, for each instruction,
+// the character offset (index into `code.chars()`) in the original source it
+// was compiled from. The offsets
+// let runtime errors (e.g. PointerOutOfBounds) point back at the source.
+pub struct Program {
+	pub instrs: Vec<Instr>,
+	offsets: Vec<usize>,
+}
+
+// Profiling counters optionally accumulated by `run`, useful for
+// diagnosing pathological loops and sizing `CAPACITY` before deployment.
+#[derive(Debug, Default)]
+pub struct Stats {
+	pub steps: usize,
+	pub inc_ptr: usize,
+	pub dec_ptr: usize,
+	pub inc_val: usize,
+	pub dec_val: usize,
+	pub clear_cell: usize,
+	pub print: usize,
+	pub scan: usize,
+	pub jump_if_zero: usize,
+	pub jump_if_nonzero: usize,
+	pub max_cell_index: usize,
+	pub peak_cell_value: u16,
+}
+
+impl Stats {
+	// Returns a zeroed Stats
+	pub fn new() -> Stats {
+		Stats::default()
+	}
+}
+
+// Errors produced while compiling or running a program. Every variant
+// carries the source offset it was raised at, except Io (std builds only)
+// which wraps whatever the injected Read/Write returned.
+#[derive(Debug)]
+pub enum BfError {
+	MismatchedBracket { offset: usize },
+	PointerOutOfBounds { offset: usize },
+	#[cfg(feature = "std")]
+	Io(io::Error),
+}
+
+impl fmt::Display for BfError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			BfError::MismatchedBracket { offset } => {
+				write!(f, "mismatched bracket at offset {}", offset)
+			}
+			BfError::PointerOutOfBounds { offset } => {
+				write!(f, "pointer out of bounds at offset {}", offset)
+			}
+			#[cfg(feature = "std")]
+			BfError::Io(e) => write!(f, "i/o error: {}", e),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl error::Error for BfError {}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for BfError {
+	fn from(e: io::Error) -> BfError {
+		BfError::Io(e)
+	}
+}
+
+// Compile source into a flat instruction stream, resolving jump targets
+// with a stack of pending `[` indices (same pattern as find_next used to
+// use, but done once instead of per loop iteration). Consecutive identical
+// operators are fused into one counted instruction as they're consumed.
+pub fn compile(code: &str) -> Result<Program, BfError> {
+	let mut instrs: Vec<Instr> = Vec::new();
+	let mut offsets: Vec<usize> = Vec::new();
+	let mut loop_stack: Vec<usize> = Vec::new();
+
+	let chars: Vec<char> = code.chars().collect();
+	let mut i: usize = 0;
+
+	while i < chars.len() {
+		let c = chars[i];
+		let start = i;
+
+		match c {
+			'>' | '<' | '+' | '-' => {
+				// Count the run of identical operators starting here
+				let mut count: usize = 1;
+				while i + count < chars.len() && chars[i + count] == c {
+					count += 1;
+				}
+				i += count;
+
+				match c {
+					'>' => instrs.push(Instr::IncPtr(count)),
+					'<' => instrs.push(Instr::DecPtr(count)),
+					'+' => instrs.push(Instr::IncVal(count as u16)),
+					'-' => instrs.push(Instr::DecVal(count as u16)),
+					_ => unreachable!(),
+				}
+				offsets.push(start);
+
+				continue;
+			}
+
+			'.' => {
+				instrs.push(Instr::Print);
+				offsets.push(start);
+			}
+
+			',' => {
+				instrs.push(Instr::Scan);
+				offsets.push(start);
+			}
+
+			'[' => {
+				// Recognize the `[-]` idiom and emit a single ClearCell. `[+]`
+				// is NOT folded the same way: it only reaches 0 under
+				// CellMode::Wrapping, which compile() has no knowledge of
+				// (the mode is a run()-time choice), so folding it would
+				// silently change behavior under Standard/Saturating cells.
+				if i + 2 < chars.len() && chars[i + 1] == '-' && chars[i + 2] == ']' {
+					instrs.push(Instr::ClearCell);
+					offsets.push(start);
+					i += 3;
+					continue;
+				}
+
+				// Placeholder target, back-patched once the matching `]` is found
+				loop_stack.push(instrs.len());
+				instrs.push(Instr::JumpIfZero(0));
+				offsets.push(start);
+			}
+
+			']' => {
+				let open = match loop_stack.pop() {
+					Some(open) => open,
+					None => return Err(BfError::MismatchedBracket { offset: start }),
+				};
+
+				// JumpIfNonZero jumps back to just past the matching `[`
+				instrs.push(Instr::JumpIfNonZero(open + 1));
+				offsets.push(start);
+
+				// Back-patch the `[` to jump to just past this `]`
+				instrs[open] = Instr::JumpIfZero(instrs.len());
+			}
+
+			_ => {
+				// Everything else (whitespace, comments, ...) is not a
+				// brainfuck command and is simply skipped
+			}
+		}
+
+		i += 1;
+	}
+
+	if let Some(&open) = loop_stack.first() {
+		return Err(BfError::MismatchedBracket { offset: offsets[open] });
+	}
+
+	Ok(Program { instrs, offsets })
+}
+
+// Run a compiled program with a single program counter. Loop entry/exit is
+// an O(1) jump instead of a recursive re-eval of the body. `Print` writes
+// to `output` and `Scan` reads from `input`; reaching EOF on `input` leaves
+// the current cell unchanged rather than panicking. Pass `Some(stats)` to
+// accumulate profiling counters as the program executes.
+pub fn run(
+	program: &Program,
+	memory: &mut Memory,
+	config: &Config,
+	input: &mut dyn Read,
+	output: &mut dyn Write,
+	mut stats: Option<&mut Stats>,
+) -> Result<(), BfError> {
+	let mut pc: usize = 0;
+
+	while pc < program.instrs.len() {
+		let offset = program.offsets[pc];
+
+		if let Some(s) = stats.as_mut() {
+			s.steps += 1;
+		}
+
+		match program.instrs[pc] {
+			Instr::IncPtr(n) => {
+				memory.inc_pointer(n, &config.pointer_mode, offset)?;
+				if let Some(s) = stats.as_mut() {
+					s.inc_ptr += 1;
+					s.max_cell_index = s.max_cell_index.max(memory.pointer);
+				}
+			}
+			Instr::DecPtr(n) => {
+				memory.dec_pointer(n, &config.pointer_mode, offset)?;
+				if let Some(s) = stats.as_mut() {
+					s.dec_ptr += 1;
+					s.max_cell_index = s.max_cell_index.max(memory.pointer);
+				}
+			}
+			Instr::IncVal(n) => {
+				memory.inc_value(n, &config.cell_mode);
+				if let Some(s) = stats.as_mut() {
+					s.inc_val += 1;
+					s.peak_cell_value = s.peak_cell_value.max(memory.data[memory.pointer]);
+				}
+			}
+			Instr::DecVal(n) => {
+				memory.dec_value(n, &config.cell_mode);
+				if let Some(s) = stats.as_mut() {
+					s.dec_val += 1;
+					s.peak_cell_value = s.peak_cell_value.max(memory.data[memory.pointer]);
+				}
+			}
+			Instr::ClearCell => {
+				memory.data[memory.pointer] = 0;
+				if let Some(s) = stats.as_mut() {
+					s.clear_cell += 1;
+				}
+			}
+
+			Instr::Print => {
+				output.write_all(&[memory.data[memory.pointer] as u8])?;
+				if let Some(s) = stats.as_mut() {
+					s.print += 1;
+				}
+			}
+
+			Instr::Scan => {
+				let mut byte = [0u8; 1];
+				if input.read(&mut byte)? != 0 {
+					memory.data[memory.pointer] = byte[0] as u16;
+				}
+				// EOF: leave the current cell unchanged
+				if let Some(s) = stats.as_mut() {
+					s.scan += 1;
+					s.peak_cell_value = s.peak_cell_value.max(memory.data[memory.pointer]);
+				}
+			}
+
+			Instr::JumpIfZero(target) => {
+				if let Some(s) = stats.as_mut() {
+					s.jump_if_zero += 1;
+				}
+				if memory.data[memory.pointer] == 0 {
+					pc = target;
+					continue;
+				}
+			}
+
+			Instr::JumpIfNonZero(target) => {
+				if let Some(s) = stats.as_mut() {
+					s.jump_if_nonzero += 1;
+				}
+				if memory.data[memory.pointer] != 0 {
+					pc = target;
+					continue;
+				}
+			}
+		}
+
+		pc += 1;
+	}
+
+	Ok(())
+}
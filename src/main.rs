@@ -1,218 +1,180 @@
 /* Usage
-   ./brainfuck filename
+   ./brainfuck filename [--stats]   run a brainfuck program from a file
+   ./brainfuck                      drop into an interactive REPL
 */
 
-use std::io;
-use std::str;
-use std::fs;
 use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
 
-// Standard brainf**k capactiy
-const CAPACITY: usize = 30000;
-
-// The main brainf**k memory
-pub struct Memory {
-	pub pointer: usize,
-	pub data: Vec<u16>,
-}
-
-impl Memory {
-	// Returns a new memory
-	pub fn new(capacity: usize) -> Memory {
-		Memory {
-			pointer: 0,
-			data: vec![0; capacity]
-		}
-	}
-}
-
-// Operation enum, each brainfuck operation is parsed into an Operation enum and given to apply function
-pub enum Operation {
-	IncPointer,
-	DecPointer,
-	IncValue,
-	DecValue,
-	Loop(usize, usize), // (start, end)
-	Print,
-	Scan,
-}
-
-// Implementation of different brainfuck operations
-impl Operation {
-	pub fn inc_pointer(memory: &mut Memory) -> () {
-		memory.pointer += 1;
-	}
-
-	pub fn dec_pointer(memory: &mut Memory) -> () {
-		memory.pointer -= 1;
-	}
-
-	pub fn inc_value(memory: &mut Memory) -> () {
-		memory.data[memory.pointer] += 1;
-	}
-
-	pub fn dec_value(memory: &mut Memory) -> () {
-		memory.data[memory.pointer] -= 1;
-	}
-
-	pub fn eval_loop(code: &str, memory: &mut Memory, operation: Operation) -> () {
-		if let Operation::Loop(a, b) = operation {
-			eval(&code[a+1..b], memory);
-		}
-	}
-
-	pub fn print(memory: &mut Memory) -> () {
-		print!("{}", memory.data[memory.pointer] as u8 as char);
-	}
-
-	pub fn scan(memory: &mut Memory) -> () {
-		let mut input = String::new();
-		io::stdin().read_line(&mut input).expect("Unable to read");
-		let raw_data: u16 = input.bytes().nth(0).expect("no byte read") as u16;
-		memory.data[memory.pointer] = raw_data;
-	}
-
-}
+use brainfuck::{compile, run, Config, Memory, Stats, CAPACITY};
 
 fn main() {
-
 	// Program arguments
-	let args: Vec<String> = env::args().collect(); 
+	let args: Vec<String> = env::args().collect();
+	let show_stats = args.iter().any(|a| a == "--stats");
+	let filename = args.iter().skip(1).find(|a| *a != "--stats");
+
+	// With no filename, fall back to the interactive REPL
+	let filename = match filename {
+		Some(filename) => filename,
+		None => {
+			repl();
+			return;
+		}
+	};
 
-	// Read code from a filename provided as a program argument
-	if args.len() < 2 {
-		panic!("Missing arguments: enter the filename")
-	}
-	let filename = &args[1];
 	let code = fs::read_to_string(filename).expect("Could not read the file.").trim().to_string();
 
 	// Memory::new returns a 0 initialized vector
 	let mut memory = Memory::new(CAPACITY);
+	let config = Config::default();
+
+	let program = compile(&code).unwrap_or_else(|e| {
+		eprintln!("{}", e);
+		process::exit(1);
+	});
+
+	let mut stdin = io::stdin();
+	let mut stdout = io::stdout();
+	let mut stats = Stats::new();
+
+	let result = run(
+		&program,
+		&mut memory,
+		&config,
+		&mut stdin,
+		&mut stdout,
+		if show_stats { Some(&mut stats) } else { None },
+	);
+
+	if show_stats {
+		print_stats(&stats);
+	}
 
-	eval(&code, &mut memory);
+	if let Err(e) = result {
+		eprintln!("{}", e);
+		process::exit(1);
+	}
 }
 
-// The 'parser', generates Operation enum based on the operation and calls apply
-fn eval(code: &str, memory: &mut Memory) -> () {
-	let mut i: usize = 0;
-	while i < code.len() {
+// Prints the summary collected by a `--stats` run
+fn print_stats(stats: &Stats) {
+	eprintln!("--- stats ---");
+	eprintln!("steps:            {}", stats.steps);
+	eprintln!("inc_ptr:          {}", stats.inc_ptr);
+	eprintln!("dec_ptr:          {}", stats.dec_ptr);
+	eprintln!("inc_val:          {}", stats.inc_val);
+	eprintln!("dec_val:          {}", stats.dec_val);
+	eprintln!("clear_cell:       {}", stats.clear_cell);
+	eprintln!("print:            {}", stats.print);
+	eprintln!("scan:             {}", stats.scan);
+	eprintln!("jump_if_zero:     {}", stats.jump_if_zero);
+	eprintln!("jump_if_nonzero:  {}", stats.jump_if_nonzero);
+	eprintln!("max_cell_index:   {}", stats.max_cell_index);
+	eprintln!("peak_cell_value:  {}", stats.peak_cell_value);
+}
 
-		match &code[i..i+1] {
+// Interactive REPL: reads a line of brainfuck at a time and executes it
+// against a persistent Memory (cells and pointer survive between lines),
+// looping until EOF or the `exit` command. An unclosed `[` makes the REPL
+// keep accumulating lines instead of erroring, so multi-line loops work.
+fn repl() {
+	let mut memory = Memory::new(CAPACITY);
+	let config = Config::default();
+	let mut stdout = io::stdout();
 
-			">" => {
-				let operation = Operation::IncPointer;
-				apply(operation, code, memory);
-			}
+	let mut buffer = String::new();
+	let mut open_brackets: i64 = 0;
 
-			"<" => {
-				let operation = Operation::DecPointer;
-				apply(operation, code, memory);
-			}
+	loop {
+		print!("{}", if buffer.is_empty() { "> " } else { "... " });
+		stdout.flush().expect("Unable to write");
 
-			"+" => {
-				let operation = Operation::IncValue;
-				apply(operation, code, memory);
-			}
+		let mut line = String::new();
+		let bytes_read = io::stdin().lock().read_line(&mut line).expect("Unable to read");
 
-			"-" => {
-				let operation = Operation::DecValue;
-				apply(operation, code, memory);
-			}
+		// EOF
+		if bytes_read == 0 {
+			println!();
+			break;
+		}
 
-			"." => {
-				let operation = Operation::Print;
-				apply(operation, code, memory);
-			}
+		let trimmed = line.trim();
 
-			"," => {
-				let operation = Operation::Scan;
-				apply(operation, code, memory);
+		if buffer.is_empty() {
+			match trimmed {
+				"exit" => break,
+				"reset" => {
+					memory = Memory::new(CAPACITY);
+					continue;
+				}
+				"dump" => {
+					dump_memory(&memory);
+					continue;
+				}
+				_ => {}
 			}
+		}
 
-			"[" => {
-
-				let start = i;
-				let end = match find_next(code, i) {
-					Some(end) => end,
-					None => panic!("Invalid code, mismatch brackets.")
-				};
-
-
-				while memory.data[memory.pointer] != 0 {
+		for c in trimmed.chars() {
+			match c {
+				'[' => open_brackets += 1,
+				']' => open_brackets -= 1,
+				_ => {}
+			}
+		}
 
-					// End is non-inclusive just like string indexing
-					let operation = Operation::Loop(start, end);
+		buffer.push_str(trimmed);
 
-					// Here we give apply the Operation and apply for the loop operation will intern
-					// call eval with a smaller code (code inside loop), we keep doing this 
-					// until the pointer points to a 0
-					// Eval<->Apply loop in place so that we can process loop(s) within loop
-					apply(operation, code, memory);
-				}
+		// Wait for more input until every `[` has been closed
+		if open_brackets > 0 {
+			continue;
+		}
 
-				// Processed the loop, move to the next operation
-				i += end - start + 1;
+		let program = match compile(&buffer) {
+			Ok(program) => program,
+			Err(e) => {
+				eprintln!("{}", e);
+				buffer.clear();
+				open_brackets = 0;
 				continue;
 			}
+		};
 
-			_ => {
-				// No match statement for "]" as the code should never reach that character,
-				// it is always skipped 
-			}
+		let mut stdin = io::stdin();
+		if let Err(e) = run(&program, &mut memory, &config, &mut stdin, &mut stdout, None) {
+			eprintln!("{}", e);
 		}
-		i += 1;
-	}
-}
+		println!();
 
-// The 'executioner', executes the Operation
-fn apply(operation: Operation, code: &str, memory: &mut Memory) -> () {
-	match operation {
-
-		Operation::IncPointer => {
-			Operation::inc_pointer(memory);
-		},
-		Operation::DecPointer => {
-			Operation::dec_pointer(memory);
-		},
-		Operation::IncValue => {
-			Operation::inc_value(memory);
-		},
-		Operation::DecValue => {
-			Operation::dec_value(memory)
-		},
-		// (_a, _b) = (start, end) 
-		Operation::Loop(_a, _b) => {
-			Operation::eval_loop(code, memory, operation);
-		},
-		Operation::Print => {
-			Operation::print(memory);
-		},
-		Operation::Scan => {
-			Operation::scan(memory);
-		},
+		buffer.clear();
+		open_brackets = 0;
 	}
 }
 
-// Find the next matching bracket, i is the opening bracket index
-fn find_next(code: &str, i: usize) -> Option<usize> {
-	
-	// Create a stack for bracket matching
-	let mut stack: Vec<usize> = Vec::new();
-
-	// Main loop, the return value in Some is non-inclusive
-	for (p, c) in code[i..].chars().enumerate(){
-		match c {
-			'[' => stack.push(1),
-			']' => {
-				if stack.len() == 1 {
-					return Some(p + i);
-				}
-				else {
-					stack.pop();
-				}
-			},
-			_ => continue,
+// How many cells to show on either side of the pointer in `dump`
+const DUMP_WINDOW: usize = 10;
+
+// Prints the nonzero cells around the pointer, marking the cell the
+// pointer is currently on.
+fn dump_memory(memory: &Memory) {
+	let start = memory.pointer.saturating_sub(DUMP_WINDOW);
+	let end = (memory.pointer + DUMP_WINDOW + 1).min(memory.data.len());
+
+	let mut printed = false;
+
+	for (i, &value) in memory.data[start..end].iter().enumerate() {
+		let i = start + i;
+		if value != 0 || i == memory.pointer {
+			let marker = if i == memory.pointer { "*" } else { " " };
+			println!("{}[{}] = {}", marker, i, value);
+			printed = true;
 		}
 	}
-	return None;
+
+	if !printed {
+		println!("(all cells zero)");
+	}
 }